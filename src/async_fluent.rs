@@ -0,0 +1,165 @@
+//! Async, non-blocking send API built on `tokio`.
+//!
+//! `AsyncFluent` mirrors `Fluent`, but drives the same `Record`/`EventRecord`/
+//! `Forward` serialization paths over `tokio::net::TcpStream` instead of
+//! `std::net::TcpStream`, so records can be posted from inside an async
+//! runtime without blocking a worker thread. Requires the `async` feature.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! extern crate fruently;
+//! use fruently::async_fluent::AsyncFluent;
+//! use std::collections::HashMap;
+//!
+//! // Run from inside a tokio runtime with its time and I/O drivers enabled.
+//! async fn send() {
+//!     let mut obj: HashMap<String, String> = HashMap::new();
+//!     obj.insert("name".to_string(), "fruently".to_string());
+//!     let fruently = AsyncFluent::new("127.0.0.1:24224", "test");
+//!     let _ = fruently.post_as_json(&obj).await;
+//! }
+//! # fn main() {}
+//! ```
+
+use crate::error::FluentError;
+#[cfg(not(feature = "time-as-integer"))]
+use crate::event_record::EventRecord;
+use crate::forwardable::forward::Forward;
+use crate::forwardable::Entry;
+use crate::record::Record;
+use crate::retry_conf::RetryConf;
+use crate::store_buffer;
+use rmp_serde::encode::Serializer;
+use serde::ser::Serialize;
+use serde_json;
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+use time;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+
+#[cfg(feature = "time-as-integer")]
+type MsgPackSendType<T> = Record<T>;
+#[cfg(not(feature = "time-as-integer"))]
+type MsgPackSendType<T> = EventRecord<T>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsyncFluent<'a, A>
+where
+    A: ToSocketAddrs,
+{
+    addr: A,
+    tag: Cow<'a, str>,
+    conf: RetryConf,
+}
+
+impl<'a, A: ToSocketAddrs> AsyncFluent<'a, A> {
+    /// Create an `AsyncFluent` type.
+    pub fn new<T>(addr: A, tag: T) -> AsyncFluent<'a, A>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        AsyncFluent {
+            addr,
+            tag: tag.into(),
+            conf: RetryConf::new(),
+        }
+    }
+
+    pub fn new_with_conf<T>(addr: A, tag: T, conf: RetryConf) -> AsyncFluent<'a, A>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        AsyncFluent {
+            addr,
+            tag: tag.into(),
+            conf,
+        }
+    }
+
+    /// Write `bytes` to a freshly-connected socket, retrying with the same
+    /// exponential backoff as the blocking `retry_exponentially` path.
+    async fn send_bytes(&self, bytes: &[u8]) -> Result<(), FluentError> {
+        let (max_retry, multiplier) = self.conf.build();
+        let addrs = self.addr.to_socket_addrs()?.collect::<Vec<_>>();
+        let mut wait = Duration::from_millis(100);
+        let mut last_err = None;
+        for _ in 0..=max_retry {
+            match TcpStream::connect(&*addrs).await {
+                Ok(mut stream) => match stream.write_all(bytes).await {
+                    Ok(()) => return Ok(()),
+                    Err(err) => last_err = Some(FluentError::from(err)),
+                },
+                Err(err) => last_err = Some(FluentError::from(err)),
+            }
+            sleep(wait).await;
+            wait = wait.mul_f64(multiplier);
+        }
+        Err(last_err.unwrap_or_else(|| FluentError::Buffer("async send exhausted retries".to_string())))
+    }
+
+    /// Post record into Fluentd as JSON. Without time version.
+    pub async fn post_as_json<T>(&self, record: T) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug + Clone,
+    {
+        self.post_as_json_with_time(record, time::now()).await
+    }
+
+    /// Post record into Fluentd as JSON. With time version.
+    pub async fn post_as_json_with_time<T>(
+        &self, record: T, time: time::Tm,
+    ) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug + Clone,
+    {
+        let record = Record::new(self.tag.clone().into_owned(), time, record);
+        let message = serde_json::to_string(&record)?;
+        match self.send_bytes(message.as_bytes()).await {
+            Ok(()) => Ok(()),
+            Err(err) => store_buffer::maybe_write_events(&self.conf, record, err),
+        }
+    }
+
+    /// Post record into Fluentd as msgpack. Without time version.
+    pub async fn post_as_msgpack<T>(&self, record: T) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        self.post_as_msgpack_with_time(record, time::now()).await
+    }
+
+    /// Post record into Fluentd as msgpack. With time version.
+    pub async fn post_as_msgpack_with_time<T>(
+        &self, record: T, time: time::Tm,
+    ) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        let record = MsgPackSendType::new(self.tag.clone().into_owned(), time, record);
+        let mut bytes = Vec::new();
+        record.serialize(&mut Serializer::new(&mut bytes))?;
+        match self.send_bytes(&bytes).await {
+            Ok(()) => Ok(()),
+            Err(err) => store_buffer::maybe_write_events(&self.conf, record, err),
+        }
+    }
+
+    /// Post a batch of `[time, record]` entries as a single `Forward` message.
+    pub async fn post_as_forward<T>(&self, entries: Vec<Entry<T>>) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        let forward = Forward::new(self.tag.clone().into_owned(), entries);
+        let mut bytes = Vec::new();
+        forward.serialize(&mut Serializer::new(&mut bytes))?;
+        match self.send_bytes(&bytes).await {
+            Ok(()) => Ok(()),
+            Err(err) => store_buffer::maybe_write_events(&self.conf, forward, err),
+        }
+    }
+}
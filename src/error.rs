@@ -0,0 +1,88 @@
+//! Error types returned from fruently operations.
+
+use rmp_serde::encode::Error as EncodeError;
+use serde_json::Error as JsonError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum FluentError {
+    Io(io::Error),
+    Encode(EncodeError),
+    Json(JsonError),
+    Buffer(String),
+    /// The Fluentd `<security>` handshake (`HELO`/`PING`/`PONG`) was rejected
+    /// or the server's digest did not match.
+    Auth(String),
+    /// `Fluent::try_post` could not write without blocking; the connection
+    /// is left open so the caller can retry once its poller reports
+    /// writable again.
+    WouldBlock,
+    /// `retry_exponentially` gave up after exhausting `RetryConf::max_retry`.
+    Retry(String),
+}
+
+impl fmt::Display for FluentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FluentError::Io(ref err) => write!(f, "IO error: {}", err),
+            FluentError::Encode(ref err) => write!(f, "msgpack encode error: {}", err),
+            FluentError::Json(ref err) => write!(f, "json error: {}", err),
+            FluentError::Buffer(ref reason) => write!(f, "buffer error: {}", reason),
+            FluentError::Auth(ref reason) => write!(f, "fluentd authentication failed: {}", reason),
+            FluentError::WouldBlock => write!(f, "write would block"),
+            FluentError::Retry(ref reason) => write!(f, "retry failed: {}", reason),
+        }
+    }
+}
+
+impl Error for FluentError {
+    fn description(&self) -> &str {
+        match *self {
+            FluentError::Io(_) => "IO error",
+            FluentError::Encode(_) => "msgpack encode error",
+            FluentError::Json(_) => "json error",
+            FluentError::Buffer(_) => "buffer error",
+            FluentError::Auth(_) => "fluentd authentication failed",
+            FluentError::WouldBlock => "write would block",
+            FluentError::Retry(_) => "retry failed",
+        }
+    }
+}
+
+impl From<io::Error> for FluentError {
+    fn from(err: io::Error) -> FluentError {
+        FluentError::Io(err)
+    }
+}
+
+impl From<EncodeError> for FluentError {
+    fn from(err: EncodeError) -> FluentError {
+        FluentError::Encode(err)
+    }
+}
+
+impl From<JsonError> for FluentError {
+    fn from(err: JsonError) -> FluentError {
+        FluentError::Json(err)
+    }
+}
+
+impl From<rmpv::decode::Error> for FluentError {
+    fn from(err: rmpv::decode::Error) -> FluentError {
+        FluentError::Buffer(format!("malformed msgpack handshake message: {}", err))
+    }
+}
+
+impl From<rmpv::encode::Error> for FluentError {
+    fn from(err: rmpv::encode::Error) -> FluentError {
+        FluentError::Buffer(format!("failed to encode msgpack handshake message: {}", err))
+    }
+}
+
+impl From<retry::RetryError> for FluentError {
+    fn from(err: retry::RetryError) -> FluentError {
+        FluentError::Retry(err.to_string())
+    }
+}
@@ -0,0 +1,40 @@
+//! Single `[tag, time, record]` entry using the extended `EventTime`, used
+//! when the `time-as-integer` feature is disabled.
+
+use crate::event_time::EventTime;
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use time;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord<T> {
+    tag: String,
+    time: EventTime,
+    record: T,
+}
+
+impl<T> EventRecord<T> {
+    pub fn new(tag: String, time: time::Tm, record: T) -> EventRecord<T> {
+        EventRecord {
+            tag,
+            time: EventTime::new(time),
+            record,
+        }
+    }
+
+    pub fn get_record(&self) -> &T {
+        &self.record
+    }
+}
+
+impl<T: Serialize> Serialize for EventRecord<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&self.tag)?;
+        seq.serialize_element(&self.time)?;
+        seq.serialize_element(&self.record)?;
+        seq.end()
+    }
+}
@@ -0,0 +1,50 @@
+//! Extended event time, used when the `time-as-integer` feature is disabled.
+
+use rmp_serde::MSGPACK_EXT_STRUCT_NAME;
+use serde::ser::{Serialize, Serializer};
+use time;
+
+/// The msgpack ext type id fluentd's forward protocol reserves for `EventTime`.
+/// See <https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1#eventtime-ext-format>.
+const EVENT_TIME_EXT_TYPE: i8 = 0;
+
+/// Wraps `time::Tm` so it serializes as fluentd's `EventTime` ext type: an
+/// 8-byte payload of big-endian seconds-since-epoch followed by big-endian
+/// nanoseconds, preserving the sub-second precision the plain-integer
+/// `Record`/`time-as-integer` path truncates away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventTime(time::Tm);
+
+impl EventTime {
+    pub fn new(time: time::Tm) -> EventTime {
+        EventTime(time)
+    }
+}
+
+/// Forces the 8-byte ext payload to serialize as msgpack `bin` (via
+/// `serialize_bytes`) rather than as a generic integer sequence, which is
+/// what `rmp_serde`'s `MSGPACK_EXT_STRUCT_NAME` convention requires of an
+/// ext type's second tuple field.
+struct ExtPayload([u8; 8]);
+
+impl Serialize for ExtPayload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl Serialize for EventTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let timespec = self.0.to_timespec();
+        let mut payload = [0u8; 8];
+        payload[0..4].copy_from_slice(&(timespec.sec as u32).to_be_bytes());
+        payload[4..8].copy_from_slice(&(timespec.nsec as u32).to_be_bytes());
+        serializer.serialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, &(EVENT_TIME_EXT_TYPE, ExtPayload(payload)))
+    }
+}
@@ -6,16 +6,29 @@ use crate::event_record::EventRecord;
 use crate::forwardable::forward::Forward;
 use crate::record::Record;
 use crate::retry_conf::RetryConf;
+use crate::serializer::{JsonSerializer, PayloadSerializer};
+use crate::store_buffer;
+use retry::retry_exponentially;
 use rmp_serde::encode::Serializer;
+use rmpv;
 use serde::ser::Serialize;
-use serde_json;
-use std::time::Duration;
 use std::borrow::{Borrow, Cow};
-use std::io::Write;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io::{self, Write};
 use std::net;
 use std::net::ToSocketAddrs;
+use time;
 
-#[derive(Debug, Clone, PartialEq)]
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, RawSocket};
+
+/// Holds an optional persistent connection in a `RefCell`, which makes
+/// `Fluent` `Send` but not `Sync`: reuse it from a single thread (or a
+/// thread-local), or wrap it in `Arc<Mutex<Fluent<..>>>` yourself to share
+/// one across worker threads.
 pub struct Fluent<'a, A>
 where
     A: ToSocketAddrs,
@@ -23,12 +36,17 @@ where
     addr: A,
     tag: Cow<'a, str>,
     conf: RetryConf,
+    conn: RefCell<Option<net::TcpStream>>,
+    /// Bytes a prior `try_post` wrote only part of before hitting
+    /// `WouldBlock`; drained before any further bytes are written so a
+    /// short write never desyncs the connection's message framing.
+    pending: RefCell<Vec<u8>>,
 }
 
 #[cfg(feature = "time-as-integer")]
-type MsgPackSendType<T> = Record<T>;
+pub(crate) type MsgPackSendType<T> = Record<T>;
 #[cfg(not(feature = "time-as-integer"))]
-type MsgPackSendType<T> = EventRecord<T>;
+pub(crate) type MsgPackSendType<T> = EventRecord<T>;
 
 impl<'a, A: ToSocketAddrs> Fluent<'a, A> {
     /// Create Fluent type.
@@ -48,9 +66,21 @@ impl<'a, A: ToSocketAddrs> Fluent<'a, A> {
             addr,
             tag: tag.into(),
             conf: RetryConf::new(),
+            conn: RefCell::new(None),
+            pending: RefCell::new(Vec::new()),
         }
     }
 
+    /// Create Fluent type with custom retry/auth/ack configuration.
+    ///
+    /// Deliberately does *not* take a `PayloadSerializer`: `Fluent` already
+    /// implements both `JsonForwardable` and `MsgpackForwardable`, each
+    /// pinned to its own format, and pinning a single serializer here would
+    /// make that impossible to use from one instance and would push a `P:
+    /// PayloadSerializer` type parameter through every constructor and doc
+    /// example in this module. A caller wanting a different (or
+    /// third-party) format picks it per call instead, via
+    /// `post_with_serializer`/`try_post_with_serializer`.
     pub fn new_with_conf<T>(addr: A, tag: T, conf: RetryConf) -> Fluent<'a, A>
     where
         T: Into<Cow<'a, str>>,
@@ -59,6 +89,8 @@ impl<'a, A: ToSocketAddrs> Fluent<'a, A> {
             addr,
             tag: tag.into(),
             conf,
+            conn: RefCell::new(None),
+            pending: RefCell::new(Vec::new()),
         }
     }
 
@@ -68,86 +100,269 @@ impl<'a, A: ToSocketAddrs> Fluent<'a, A> {
     }
 
     #[doc(hidden)]
-    pub fn get_tag(&'a self) -> Cow<'a, str> {
+    pub fn get_tag(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.tag)
     }
 
     #[doc(hidden)]
-    pub fn get_conf(&self) -> Cow<RetryConf> {
+    pub fn get_conf(&self) -> Cow<'_, RetryConf> {
         Cow::Borrowed(&self.conf)
     }
 
-    #[doc(hidden)]
-    /// For internal usage.
-    pub fn closure_send_as_json<T: Serialize>(
-        addr: &A, record: &Record<T>,
-    ) -> Result<(), FluentError>
+    /// Connect now (running the `<security>` handshake if configured) if no
+    /// connection is open yet. Called by `with_connection` and `try_post`,
+    /// and public so callers can force a connection before handing the
+    /// socket to `AsRawFd`/`AsRawSocket`.
+    pub fn ensure_connected(&self) -> Result<(), FluentError> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.is_none() {
+            let mut stream = net::TcpStream::connect(&self.addr)?;
+            if self.conf.get_shared_key().is_some() {
+                crate::handshake::authenticate(&mut stream, &self.conf)?;
+            }
+            *conn = Some(stream);
+        }
+        Ok(())
+    }
+
+    /// Run `write` against the held connection, connecting lazily if none is
+    /// open yet. On failure the stale connection is dropped so the next call
+    /// (typically the next `retry_exponentially` attempt) reconnects.
+    fn with_connection<F>(&self, write: F) -> Result<(), FluentError>
+    where
+        F: FnOnce(&mut net::TcpStream) -> Result<(), FluentError>,
     {
-        let result = net::TcpStream::connect(addr);
-        match result {
-            Ok(mut stream) => {
-                let message = serde_json::to_string(&record)?;
-                let wr_result = stream.write(&message.into_bytes());
-                drop(stream);
-                if wr_result.is_err() {
-                    return Err(From::from(wr_result.unwrap_err()));
-                }
-                return Ok(());
+        self.ensure_connected()?;
+        let mut conn = self.conn.borrow_mut();
+        match write(conn.as_mut().unwrap()) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                *conn = None;
+                Err(err)
             },
-            Err(v) => {
-                return Err(From::from(v));
+        }
+    }
+
+    /// Non-blocking counterpart of `JsonForwardable::post`, for callers
+    /// running their own `select`/`epoll` loop instead of retrying here.
+    /// Attempts a non-blocking write and returns `Err(FluentError::WouldBlock)`
+    /// (keeping the connection open) instead of spinning the retry loop;
+    /// register `as_raw_fd()`/`as_raw_socket()` with the external poller and
+    /// call this again once it is writable.
+    ///
+    /// A write that only partially lands is never silently treated as a
+    /// success: the unsent remainder is buffered and drained on the next
+    /// call (ahead of any new record), so message framing is never desynced.
+    /// Once everything is flushed the connection is restored to blocking
+    /// mode, so a later plain `post`/`closure_send` doesn't spuriously see
+    /// `WouldBlock`.
+    pub fn try_post<T>(&self, record: T) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        self.try_post_with_serializer(record, &JsonSerializer)
+    }
+
+    /// Generic counterpart of `try_post`, using `serializer` instead of
+    /// pinning the wire format to JSON, so a caller also sending msgpack
+    /// via `post_with_serializer` on the same `Fluent` doesn't interleave
+    /// differently-framed messages onto one connection.
+    pub fn try_post_with_serializer<P, T>(&self, record: T, serializer: &P) -> Result<(), FluentError>
+    where
+        P: PayloadSerializer,
+        T: Serialize + Debug,
+    {
+        self.ensure_connected()?;
+        let mut conn = self.conn.borrow_mut();
+        conn.as_mut().unwrap().set_nonblocking(true)?;
+
+        let mut pending = self.pending.borrow_mut();
+        if !pending.is_empty() {
+            match Self::write_nonblocking(conn.as_mut().unwrap(), &pending) {
+                Ok(written) => {
+                    pending.drain(..written);
+                    if !pending.is_empty() {
+                        return Err(FluentError::WouldBlock);
+                    }
+                },
+                Err(err) => {
+                    *conn = None;
+                    return Err(err);
+                },
+            }
+        }
+
+        let record = Record::new(self.get_tag().into_owned(), time::now(), record);
+        let bytes = serializer.serialize(&record)?;
+        match Self::write_nonblocking(conn.as_mut().unwrap(), &bytes) {
+            Ok(written) if written < bytes.len() => {
+                pending.extend_from_slice(&bytes[written..]);
+                Err(FluentError::WouldBlock)
+            },
+            Ok(_) => {
+                conn.as_mut().unwrap().set_nonblocking(false)?;
+                Ok(())
+            },
+            Err(err) => {
+                *conn = None;
+                Err(err)
             },
         }
     }
 
+    /// Writes as much of `bytes` as fits without blocking, returning `0`
+    /// (not an error) when the socket isn't writable at all yet.
+    fn write_nonblocking(stream: &mut net::TcpStream, bytes: &[u8]) -> Result<usize, FluentError> {
+        match stream.write(bytes) {
+            Ok(written) => Ok(written),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(err) => Err(FluentError::from(err)),
+        }
+    }
+
     #[doc(hidden)]
-    /// For internal usage.
-    pub fn closure_send_as_msgpack<T: Serialize>(
-        addr: &A, record: &MsgPackSendType<T>,
+    /// For internal usage. Generic over both the payload (`Record<T>` for
+    /// JSON, `MsgPackSendType<T>` for msgpack, ...) and the `PayloadSerializer`
+    /// driving it, so `JsonForwardable` and `MsgpackForwardable` share this
+    /// one send path instead of each hand-rolling their own.
+    pub fn closure_send<P: PayloadSerializer, T: Serialize>(
+        &self, value: &T, serializer: &P,
     ) -> Result<(), FluentError> {
-        let result = net::TcpStream::connect(addr);
-        match result {
-            Ok(mut stream) => {
-                let wr_result = record.serialize(&mut Serializer::new(&mut stream));
-                drop(stream);
-                if wr_result.is_err() {
-                    return Err(From::from(wr_result.unwrap_err()));
-                }
-                return Ok(());
-            },
-            Err(v) => {
-                return Err(From::from(v));
-            },
+        let bytes = serializer.serialize(value)?;
+        self.with_connection(|stream| {
+            stream.write_all(&bytes)?;
+            Ok(())
+        })
+    }
+
+    /// Post `record` using any `PayloadSerializer`, e.g. a third-party wire
+    /// format registered by a downstream crate. `JsonForwardable` is this
+    /// same path pinned to the built-in `JsonSerializer`. `MsgpackForwardable`
+    /// shares `closure_send` with it but sends a `MsgPackSendType<T>` (an
+    /// `EventTime` unless `time-as-integer` is enabled); this method always
+    /// sends a `Record<T>` with whole-second time, whatever the serializer.
+    pub fn post_with_serializer<P, T>(&self, record: T, serializer: &P) -> Result<(), FluentError>
+    where
+        P: PayloadSerializer,
+        T: Serialize + Debug,
+    {
+        let record = Record::new(self.get_tag().into_owned(), time::now(), record);
+        let (max_retry, multiplier) = self.get_conf().into_owned().build();
+        match retry_exponentially(
+            max_retry as u64,
+            multiplier,
+            || self.closure_send(&record, serializer),
+            |response| response.is_ok(),
+        ) {
+            Ok(_) => Ok(()),
+            Err(err) => store_buffer::maybe_write_events(&self.get_conf(), record, From::from(err)),
         }
     }
 
     #[doc(hidden)]
     /// For internal usage.
-    pub fn closure_send_as_forward<T: Serialize>(
-        addr: &A, forward: &Forward<T>,
-    ) -> Result<(), FluentError> {
-        let result = net::TcpStream::connect(addr);
-        match result {
-            Ok(mut stream) => {
-                let wr_result = forward.serialize(&mut Serializer::new(&mut stream));
-                drop(stream);
-                if wr_result.is_err() {
-                    return Err(From::from(wr_result.unwrap_err()));
-                }
-                return Ok(());
-            },
-            Err(v) => {
-                return Err(From::from(v));
-            },
+    pub fn closure_send_as_forward<T: Serialize>(&self, forward: &Forward<T>) -> Result<(), FluentError> {
+        let ack_timeout = self.conf.get_ack_timeout();
+        self.with_connection(|stream| {
+            forward.serialize(&mut Serializer::new(&mut *stream))?;
+            if let Some(expected_chunk) = forward.get_chunk_id() {
+                stream.set_read_timeout(Some(ack_timeout))?;
+                check_chunk_ack(stream, expected_chunk, ack_timeout)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Reads the `{"ack": <id>}` response a chunk-id'd `Forward` message expects
+/// and checks it matches `expected_chunk`. Generic over `Read` (rather than
+/// `net::TcpStream` directly) so the ack/mismatch/timeout-fallback paths can
+/// be exercised against an in-memory stream in tests; the read timeout
+/// itself is a `TcpStream`-specific concern the caller sets up beforehand.
+fn check_chunk_ack<S: io::Read>(
+    stream: &mut S, expected_chunk: &str, ack_timeout: std::time::Duration,
+) -> Result<(), FluentError> {
+    let response = rmpv::decode::read_value(stream).map_err(|err| {
+        FluentError::Buffer(format!("no chunk ack within {:?}: {}", ack_timeout, err))
+    })?;
+    let ack = response
+        .as_map()
+        .and_then(|fields| fields.iter().find(|(k, _)| k.as_str() == Some("ack")))
+        .and_then(|(_, v)| v.as_str())
+        .ok_or_else(|| FluentError::Buffer("missing ack in chunk response".to_string()))?;
+    if ack != expected_chunk {
+        return Err(FluentError::Buffer(format!(
+            "chunk ack mismatch: expected {}, got {}",
+            expected_chunk, ack
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+impl<'a, A: ToSocketAddrs> AsRawFd for Fluent<'a, A> {
+    /// Connects now (running the `<security>` handshake if configured) when
+    /// no connection is open yet, so a caller registering this fd with their
+    /// own poller before ever sending doesn't have to know to call
+    /// `ensure_connected` first. Panics only if establishing that connection
+    /// fails.
+    fn as_raw_fd(&self) -> RawFd {
+        self.ensure_connected().expect("Fluent::as_raw_fd failed to establish a connection");
+        self.conn.borrow().as_ref().unwrap().as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl<'a, A: ToSocketAddrs> AsRawSocket for Fluent<'a, A> {
+    /// Connects now (running the `<security>` handshake if configured) when
+    /// no connection is open yet, so a caller registering this socket with
+    /// their own poller before ever sending doesn't have to know to call
+    /// `ensure_connected` first. Panics only if establishing that connection
+    /// fails.
+    fn as_raw_socket(&self) -> RawSocket {
+        self.ensure_connected().expect("Fluent::as_raw_socket failed to establish a connection");
+        self.conn.borrow().as_ref().unwrap().as_raw_socket()
+    }
+}
+
+impl<'a, A: ToSocketAddrs + Clone> Clone for Fluent<'a, A> {
+    /// Cloning a `Fluent` does not carry over its open connection or any
+    /// buffered partial write; the clone reconnects lazily on its first send.
+    fn clone(&self) -> Fluent<'a, A> {
+        Fluent {
+            addr: self.addr.clone(),
+            tag: self.tag.clone(),
+            conf: self.conf.clone(),
+            conn: RefCell::new(None),
+            pending: RefCell::new(Vec::new()),
         }
     }
 }
 
+impl<'a, A: ToSocketAddrs + ::std::fmt::Debug> ::std::fmt::Debug for Fluent<'a, A> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Fluent")
+            .field("addr", &self.addr)
+            .field("tag", &self.tag)
+            .field("conf", &self.conf)
+            .finish()
+    }
+}
+
+impl<'a, A: ToSocketAddrs + PartialEq> PartialEq for Fluent<'a, A> {
+    fn eq(&self, other: &Fluent<'a, A>) -> bool {
+        self.addr == other.addr && self.tag == other.tag && self.conf == other.conf
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::retry_conf::RetryConf;
+    use rmpv::Value;
     use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::time::Duration;
 
     #[test]
     fn create_fruently() {
@@ -156,7 +371,50 @@ mod tests {
             addr: "127.0.0.1:24224",
             tag: Cow::Borrowed("test"),
             conf: RetryConf::new(),
+            conn: RefCell::new(None),
+            pending: RefCell::new(Vec::new()),
         };
         assert_eq!(expected, fruently);
     }
+
+    fn ack_bytes(chunk_id: &str) -> Vec<u8> {
+        let ack = Value::Map(vec![(Value::from("ack"), Value::from(chunk_id))]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &ack).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn check_chunk_ack_accepts_matching_ack() {
+        let bytes = ack_bytes("abc123");
+        let mut stream = bytes.as_slice();
+        check_chunk_ack(&mut stream, "abc123", Duration::from_secs(1))
+            .expect("matching ack should be accepted");
+    }
+
+    #[test]
+    fn check_chunk_ack_rejects_mismatched_ack() {
+        let bytes = ack_bytes("wrong-id");
+        let mut stream = bytes.as_slice();
+        match check_chunk_ack(&mut stream, "abc123", Duration::from_secs(1)) {
+            Err(FluentError::Buffer(reason)) => {
+                assert_eq!(reason, "chunk ack mismatch: expected abc123, got wrong-id");
+            },
+            other => panic!("expected a mismatch error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_chunk_ack_falls_back_when_no_ack_arrives() {
+        // An empty stream fails exactly the way a read timing out with no
+        // bytes yet read would: `rmpv::decode::read_value` errors out, which
+        // gets wrapped into the same "no chunk ack" message either way.
+        let mut stream: &[u8] = &[];
+        match check_chunk_ack(&mut stream, "abc123", Duration::from_secs(1)) {
+            Err(FluentError::Buffer(reason)) => {
+                assert!(reason.starts_with("no chunk ack within"), "got: {}", reason);
+            },
+            other => panic!("expected a no-ack error, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,197 @@
+//! Send record(s) as a single forward-protocol `[tag, entries]` message,
+//! optionally packed and gzip-compressed as `CompressedPackedForward`.
+
+use crate::error::FluentError;
+use crate::fluent::Fluent;
+use crate::forwardable::{Entry, Forwardable};
+use crate::store_buffer;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rand::Rng;
+use retry::retry_exponentially;
+use rmp_serde::encode::Serializer as MsgpackSerializer;
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::Write;
+use std::net::ToSocketAddrs;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Body<T> {
+    Entries(Vec<Entry<T>>),
+    CompressedPacked(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forward<T> {
+    tag: String,
+    body: Body<T>,
+    chunk_id: Option<String>,
+}
+
+impl<T> Forward<T> {
+    pub fn new(tag: String, entries: Vec<Entry<T>>) -> Forward<T> {
+        Forward { tag, body: Body::Entries(entries), chunk_id: None }
+    }
+
+    /// Build a `Forward` message whose entries are packed into a single
+    /// msgpack-serialized blob and gzip-compressed, i.e. Fluentd's
+    /// `CompressedPackedForward` carrier.
+    pub fn compressed(tag: String, entries: Vec<Entry<T>>) -> Result<Forward<T>, FluentError>
+    where
+        T: Serialize,
+    {
+        let mut packed = Vec::new();
+        for entry in &entries {
+            entry.serialize(&mut MsgpackSerializer::new(&mut packed))?;
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&packed)?;
+        let gzipped = encoder.finish()?;
+        Ok(Forward { tag, body: Body::CompressedPacked(gzipped), chunk_id: None })
+    }
+
+    /// Opt the message into the forward protocol's `require_ack` chunk
+    /// acknowledgement, identified by `chunk_id`.
+    pub fn with_chunk_id(mut self, chunk_id: String) -> Forward<T> {
+        self.chunk_id = Some(chunk_id);
+        self
+    }
+
+    pub fn get_chunk_id(&self) -> Option<&str> {
+        self.chunk_id.as_deref()
+    }
+
+    /// Returns the uncompressed entries, or `None` when this is a
+    /// `CompressedPackedForward` message.
+    pub fn get_entries(&self) -> Option<&[Entry<T>]> {
+        match self.body {
+            Body::Entries(ref entries) => Some(entries),
+            Body::CompressedPacked(_) => None,
+        }
+    }
+}
+
+/// Forces `Vec<u8>` to serialize as msgpack `bin` rather than as a generic
+/// sequence of integers.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<T: Serialize> Serialize for Forward<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut option = HashMap::new();
+        if let Some(ref chunk_id) = self.chunk_id {
+            option.insert("chunk", chunk_id.clone());
+        }
+        if let Body::CompressedPacked(_) = self.body {
+            option.insert("compressed", "gzip".to_string());
+        }
+
+        let mut seq = serializer.serialize_seq(Some(if option.is_empty() { 2 } else { 3 }))?;
+        seq.serialize_element(&self.tag)?;
+        match self.body {
+            Body::Entries(ref entries) => seq.serialize_element(entries)?,
+            Body::CompressedPacked(ref bytes) => seq.serialize_element(&Bytes(bytes))?,
+        }
+        if !option.is_empty() {
+            seq.serialize_element(&option)?;
+        }
+        seq.end()
+    }
+}
+
+/// A base64-encoded, 16-random-byte id unique enough to pair a `Forward`
+/// message with its `{"ack": <id>}` response.
+fn generate_chunk_id() -> String {
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..16).map(|_| rng.gen()).collect();
+    base64::encode(&bytes)
+}
+
+impl<'a, A: ToSocketAddrs> Forwardable for Fluent<'a, A> {
+    /// Post a batch of `[time, record]` entries as a single `Forward` message.
+    ///
+    /// When `RetryConf::require_ack` is set, the message carries a chunk id
+    /// and this blocks until the matching `{"ack": <id>}` is read back.
+    /// When `RetryConf::compressed` is set, the entries are sent as a single
+    /// gzipped `CompressedPackedForward` blob instead. Neither is set by
+    /// default, so existing behavior is unchanged.
+    fn post<T>(&self, entries: Vec<Entry<T>>) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        let tag = self.get_tag().into_owned();
+        let mut forward = if self.get_conf().get_compressed() {
+            Forward::compressed(tag, entries)?
+        } else {
+            Forward::new(tag, entries)
+        };
+        if self.get_conf().get_require_ack() {
+            forward = forward.with_chunk_id(generate_chunk_id());
+        }
+        let (max_retry, multiplier) = self.get_conf().into_owned().build();
+        match retry_exponentially(
+            max_retry as u64,
+            multiplier,
+            || self.closure_send_as_forward(&forward),
+            |response| response.is_ok(),
+        ) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                store_buffer::maybe_write_events(&self.get_conf(), forward, From::from(err))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmpv;
+
+    #[test]
+    fn serialize_with_chunk_id_includes_chunk_option() {
+        let forward = Forward::new("test.tag".to_string(), Vec::<Entry<String>>::new())
+            .with_chunk_id("abc123".to_string());
+        let mut bytes = Vec::new();
+        forward.serialize(&mut MsgpackSerializer::new(&mut bytes)).unwrap();
+
+        let value = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+        let fields = value.as_array().unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].as_str(), Some("test.tag"));
+        let chunk = fields[2]
+            .as_map()
+            .and_then(|option| option.iter().find(|(k, _)| k.as_str() == Some("chunk")))
+            .and_then(|(_, v)| v.as_str());
+        assert_eq!(chunk, Some("abc123"));
+    }
+
+    #[test]
+    fn serialize_compressed_includes_compressed_option_and_gzip_bytes() {
+        let forward = Forward::compressed("test.tag".to_string(), Vec::<Entry<String>>::new()).unwrap();
+        let mut bytes = Vec::new();
+        forward.serialize(&mut MsgpackSerializer::new(&mut bytes)).unwrap();
+
+        let value = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+        let fields = value.as_array().unwrap();
+        assert_eq!(fields.len(), 3);
+        assert!(fields[1].as_slice().is_some(), "entries should be encoded as bin, not an array");
+        let compressed = fields[2]
+            .as_map()
+            .and_then(|option| option.iter().find(|(k, _)| k.as_str() == Some("compressed")))
+            .and_then(|(_, v)| v.as_str());
+        assert_eq!(compressed, Some("gzip"));
+    }
+}
@@ -22,6 +22,7 @@ use crate::error::FluentError;
 use crate::fluent::Fluent;
 use crate::forwardable::JsonForwardable;
 use crate::record::Record;
+use crate::serializer::JsonSerializer;
 use crate::store_buffer;
 use retry::retry_exponentially;
 use serde::ser::Serialize;
@@ -31,7 +32,7 @@ use time;
 
 impl<'a, A: ToSocketAddrs> JsonForwardable for Fluent<'a, A> {
     /// Post record into Fluentd. Without time version.
-    fn post<T>(self, record: T) -> Result<(), FluentError>
+    fn post<T>(&self, record: T) -> Result<(), FluentError>
     where
         T: Serialize + Debug + Clone,
     {
@@ -40,17 +41,16 @@ impl<'a, A: ToSocketAddrs> JsonForwardable for Fluent<'a, A> {
     }
 
     /// Post record into Fluentd. With time version.
-    fn post_with_time<T>(self, record: T, time: time::Tm) -> Result<(), FluentError>
+    fn post_with_time<T>(&self, record: T, time: time::Tm) -> Result<(), FluentError>
     where
         T: Serialize + Debug + Clone,
     {
         let record = Record::new(self.get_tag().into_owned(), time, record);
-        let addr = self.get_addr();
         let (max_retry, multiplier) = self.get_conf().into_owned().build();
         match retry_exponentially(
-            max_retry,
+            max_retry as u64,
             multiplier,
-            || Fluent::closure_send_as_json(addr, &record),
+            || self.closure_send(&record, &JsonSerializer),
             |response| response.is_ok(),
         ) {
             Ok(_) => Ok(()),
@@ -13,21 +13,21 @@ pub type Entry<T> = (EventTime, T);
 pub type Entry<T> = (i64, T);
 
 pub trait JsonForwardable {
-    fn post<T: Serialize + Debug + Clone>(self, record: T) -> Result<(), FluentError>;
+    fn post<T: Serialize + Debug + Clone>(&self, record: T) -> Result<(), FluentError>;
     fn post_with_time<T: Serialize + Debug + Clone>(
-        self, record: T, time: time::Tm
+        &self, record: T, time: time::Tm
     ) -> Result<(), FluentError>;
 }
 
 pub trait MsgpackForwardable {
-    fn post<T: Serialize + Debug>(self, record: T) -> Result<(), FluentError>;
+    fn post<T: Serialize + Debug>(&self, record: T) -> Result<(), FluentError>;
     fn post_with_time<T: Serialize + Debug>(
-        self, record: T, time: time::Tm,
+        &self, record: T, time: time::Tm,
     ) -> Result<(), FluentError>;
 }
 
 pub trait Forwardable {
-    fn post<T: Serialize + Debug>(self, entries: Vec<Entry<T>>) -> Result<(), FluentError>;
+    fn post<T: Serialize + Debug>(&self, entries: Vec<Entry<T>>) -> Result<(), FluentError>;
 }
 
 pub mod forward;
@@ -0,0 +1,59 @@
+//! Send record as msgpack.
+//!
+//! ## Usage
+//!
+//! This trait is used as follows:
+//!
+//! ```no_run
+//! extern crate fruently;
+//! use fruently::fluent::Fluent;
+//! use std::collections::HashMap;
+//! use fruently::forwardable::MsgpackForwardable;
+//!
+//! fn main() {
+//!     let mut obj: HashMap<String, String> = HashMap::new();
+//!     obj.insert("name".to_string(), "fruently".to_string());
+//!     let fruently = Fluent::new("127.0.0.1:24224", "test");
+//!     let _ = fruently.post(&obj);
+//! }
+//! ```
+
+use crate::error::FluentError;
+use crate::fluent::{Fluent, MsgPackSendType};
+use crate::forwardable::MsgpackForwardable;
+use crate::serializer::MsgpackSerializer;
+use crate::store_buffer;
+use retry::retry_exponentially;
+use serde::ser::Serialize;
+use std::fmt::Debug;
+use std::net::ToSocketAddrs;
+use time;
+
+impl<'a, A: ToSocketAddrs> MsgpackForwardable for Fluent<'a, A> {
+    /// Post record into Fluentd. Without time version.
+    fn post<T>(&self, record: T) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        let time = time::now();
+        self.post_with_time(record, time)
+    }
+
+    /// Post record into Fluentd. With time version.
+    fn post_with_time<T>(&self, record: T, time: time::Tm) -> Result<(), FluentError>
+    where
+        T: Serialize + Debug,
+    {
+        let record = MsgPackSendType::new(self.get_tag().into_owned(), time, record);
+        let (max_retry, multiplier) = self.get_conf().into_owned().build();
+        match retry_exponentially(
+            max_retry as u64,
+            multiplier,
+            || self.closure_send(&record, &MsgpackSerializer),
+            |response| response.is_ok(),
+        ) {
+            Ok(_) => Ok(()),
+            Err(err) => store_buffer::maybe_write_events(&self.get_conf(), record, From::from(err)),
+        }
+    }
+}
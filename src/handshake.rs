@@ -0,0 +1,254 @@
+//! The forward protocol's `<security>` handshake (`HELO`/`PING`/`PONG`),
+//! used to authenticate against a Fluentd input configured with a shared key
+//! and, optionally, a username/password pair.
+//!
+//! See <https://docs.fluentd.org/input/forward#security>.
+
+use crate::error::FluentError;
+use crate::retry_conf::RetryConf;
+use rand::Rng;
+use rmpv::Value;
+use sha2::{Digest, Sha512};
+use std::io::{Read, Write};
+
+/// Run the handshake over a freshly-connected `stream`. Returns once the
+/// server's `PONG` digest has been verified. A `HELO`/`PONG` of the wrong
+/// shape, a rejected `PONG` or a digest mismatch is a `FluentError::Auth`;
+/// a failure to read or write a msgpack message on `stream` is a
+/// `FluentError::Buffer`.
+pub fn authenticate<S: Read + Write>(stream: &mut S, conf: &RetryConf) -> Result<(), FluentError> {
+    let helo = rmpv::decode::read_value(stream)?;
+    let (nonce, auth_salt) = parse_helo(&helo)?;
+
+    let shared_key = conf.get_shared_key().unwrap_or("");
+    let self_hostname = conf.get_self_hostname().unwrap_or("");
+    let username = conf.get_username().unwrap_or("");
+    let password = conf.get_password().unwrap_or("");
+
+    let shared_key_salt = random_bytes(16);
+    let shared_key_digest = hexdigest(&[&shared_key_salt, self_hostname.as_bytes(), &nonce, shared_key.as_bytes()]);
+    let password_digest = hexdigest(&[auth_salt.as_bytes(), username.as_bytes(), password.as_bytes()]);
+
+    let ping = Value::Array(vec![
+        Value::from("PING"),
+        Value::from(self_hostname),
+        Value::Binary(shared_key_salt.clone()),
+        Value::from(shared_key_digest),
+        Value::from(username),
+        Value::from(password_digest),
+    ]);
+    rmpv::encode::write_value(stream, &ping)?;
+
+    let pong = rmpv::decode::read_value(stream)?;
+    let (auth_result, reason, server_hostname, server_digest) = parse_pong(&pong)?;
+    if !auth_result {
+        return Err(FluentError::Auth(reason));
+    }
+
+    let expected_digest =
+        hexdigest(&[&shared_key_salt, server_hostname.as_bytes(), &nonce, shared_key.as_bytes()]);
+    if expected_digest != server_digest {
+        return Err(FluentError::Auth("PONG shared-key digest mismatch".to_string()));
+    }
+    Ok(())
+}
+
+fn parse_helo(message: &Value) -> Result<(Vec<u8>, String), FluentError> {
+    let fields = message
+        .as_array()
+        .filter(|fields| fields.len() == 2 && fields[0].as_str() == Some("HELO"))
+        .ok_or_else(|| FluentError::Auth("expected a HELO message".to_string()))?;
+    let options = &fields[1];
+    let nonce = map_get(options, "nonce")
+        .and_then(Value::as_slice)
+        .ok_or_else(|| FluentError::Auth("HELO is missing nonce".to_string()))?
+        .to_vec();
+    let auth = map_get(options, "auth").and_then(Value::as_str).unwrap_or("").to_string();
+    Ok((nonce, auth))
+}
+
+/// Look up `key` in a msgpack `Value::Map`, the shape fluentd uses for the
+/// `HELO` option map.
+fn map_get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value.as_map()?.iter().find(|(k, _)| k.as_str() == Some(key)).map(|(_, v)| v)
+}
+
+fn parse_pong(message: &Value) -> Result<(bool, String, String, String), FluentError> {
+    let fields = message
+        .as_array()
+        .filter(|fields| fields.len() == 5 && fields[0].as_str() == Some("PONG"))
+        .ok_or_else(|| FluentError::Auth("expected a PONG message".to_string()))?;
+    let auth_result = fields[1]
+        .as_bool()
+        .ok_or_else(|| FluentError::Auth("PONG auth_result is not a bool".to_string()))?;
+    let reason = fields[2].as_str().unwrap_or("").to_string();
+    let server_hostname = fields[3].as_str().unwrap_or("").to_string();
+    let digest = fields[4].as_str().unwrap_or("").to_string();
+    Ok((auth_result, reason, server_hostname, digest))
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+fn hexdigest(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const NONCE: &[u8] = b"test-nonce";
+    const SERVER_HOSTNAME: &str = "server.example";
+    const SHARED_KEY: &str = "supersecret";
+
+    fn helo_bytes() -> Vec<u8> {
+        let helo = Value::Array(vec![
+            Value::from("HELO"),
+            Value::Map(vec![
+                (Value::from("nonce"), Value::Binary(NONCE.to_vec())),
+                (Value::from("auth"), Value::from("")),
+            ]),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &helo).unwrap();
+        bytes
+    }
+
+    fn ping_shared_key_salt(ping: &Value) -> Vec<u8> {
+        ping.as_array().unwrap()[2].as_slice().unwrap().to_vec()
+    }
+
+    /// Serves a canned `HELO`; once it has seen the client's `PING`, derives
+    /// the `PONG` digest from the salt the client sent, the way a real
+    /// Fluentd server would. `corrupt_digest` asks for a deliberately wrong
+    /// one instead, to exercise the mismatch path.
+    struct MockServer {
+        pending_read: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+        corrupt_digest: bool,
+    }
+
+    impl MockServer {
+        fn new(corrupt_digest: bool) -> MockServer {
+            MockServer { pending_read: Cursor::new(helo_bytes()), written: Vec::new(), corrupt_digest }
+        }
+
+        fn ping(&self) -> Value {
+            rmpv::decode::read_value(&mut self.written.as_slice()).unwrap()
+        }
+    }
+
+    impl Read for MockServer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let helo_exhausted = self.pending_read.position() as usize >= self.pending_read.get_ref().len();
+            if helo_exhausted && !self.written.is_empty() {
+                let salt = ping_shared_key_salt(&self.ping());
+                let digest = if self.corrupt_digest {
+                    "0".repeat(128)
+                } else {
+                    hexdigest(&[&salt, SERVER_HOSTNAME.as_bytes(), NONCE, SHARED_KEY.as_bytes()])
+                };
+                let pong = Value::Array(vec![
+                    Value::from("PONG"),
+                    Value::from(true),
+                    Value::from(""),
+                    Value::from(SERVER_HOSTNAME),
+                    Value::from(digest),
+                ]);
+                let mut bytes = Vec::new();
+                rmpv::encode::write_value(&mut bytes, &pong).unwrap();
+                self.pending_read = Cursor::new(bytes);
+            }
+            self.pending_read.read(buf)
+        }
+    }
+
+    impl Write for MockServer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn authenticate_sends_expected_ping_and_accepts_matching_pong() {
+        let conf = RetryConf::new()
+            .shared_key(SHARED_KEY)
+            .self_hostname("client.example")
+            .username("alice")
+            .password("hunter2");
+        let mut server = MockServer::new(false);
+
+        authenticate(&mut server, &conf).expect("handshake should succeed against a matching PONG");
+
+        let ping = server.ping();
+        let fields = ping.as_array().unwrap();
+        assert_eq!(fields[0].as_str(), Some("PING"));
+        assert_eq!(fields[1].as_str(), Some("client.example"));
+        assert_eq!(fields[4].as_str(), Some("alice"));
+        let expected_password_digest = hexdigest(&[b"", b"alice", b"hunter2"]);
+        assert_eq!(fields[5].as_str(), Some(expected_password_digest.as_str()));
+    }
+
+    #[test]
+    fn authenticate_rejects_mismatched_pong_digest() {
+        let conf = RetryConf::new().shared_key(SHARED_KEY);
+        let mut server = MockServer::new(true);
+
+        match authenticate(&mut server, &conf) {
+            Err(FluentError::Auth(reason)) => assert_eq!(reason, "PONG shared-key digest mismatch"),
+            other => panic!("expected digest-mismatch Auth error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn authenticate_rejects_server_reported_auth_failure() {
+        struct Rejecting(Cursor<Vec<u8>>);
+        impl Read for Rejecting {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+        impl Write for Rejecting {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let pong = Value::Array(vec![
+            Value::from("PONG"),
+            Value::from(false),
+            Value::from("bad shared key"),
+            Value::from(SERVER_HOSTNAME),
+            Value::from(""),
+        ]);
+        let mut combined = helo_bytes();
+        rmpv::encode::write_value(&mut combined, &pong).unwrap();
+
+        let conf = RetryConf::new().shared_key(SHARED_KEY);
+        let mut stream = Rejecting(Cursor::new(combined));
+        match authenticate(&mut stream, &conf) {
+            Err(FluentError::Auth(reason)) => assert_eq!(reason, "bad shared key"),
+            other => panic!("expected Auth error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Asynchronous, simple and resilient fluentd logger.
+//!
+//! This crate posts records to [fluentd](https://www.fluentd.org/) using the
+//! [forward protocol](https://docs.fluentd.org/input/forward).
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! extern crate fruently;
+//! use fruently::fluent::Fluent;
+//! use fruently::forwardable::JsonForwardable;
+//! use std::collections::HashMap;
+//!
+//! fn main() {
+//!     let mut obj: HashMap<String, String> = HashMap::new();
+//!     obj.insert("name".to_string(), "fruently".to_string());
+//!     let fruently = Fluent::new("127.0.0.1:24224", "test");
+//!     let _ = fruently.post(&obj);
+//! }
+//! ```
+
+extern crate base64;
+extern crate flate2;
+extern crate rand;
+extern crate retry;
+extern crate rmp_serde;
+extern crate rmpv;
+extern crate serde;
+extern crate serde_json;
+extern crate sha2;
+extern crate time;
+
+#[cfg(feature = "async")]
+pub mod async_fluent;
+pub mod error;
+pub mod event_record;
+pub mod event_time;
+pub mod fluent;
+pub mod forwardable;
+mod handshake;
+pub mod record;
+pub mod retry_conf;
+pub mod serializer;
+pub mod store_buffer;
@@ -0,0 +1,38 @@
+//! Single `[tag, time, record]` entry sent over the forward protocol.
+
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use time;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<T> {
+    tag: String,
+    time: i64,
+    record: T,
+}
+
+impl<T> Record<T> {
+    pub fn new(tag: String, time: time::Tm, record: T) -> Record<T> {
+        Record {
+            tag,
+            time: time.to_timespec().sec,
+            record,
+        }
+    }
+
+    pub fn get_record(&self) -> &T {
+        &self.record
+    }
+}
+
+impl<T: Serialize> Serialize for Record<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&self.tag)?;
+        seq.serialize_element(&self.time)?;
+        seq.serialize_element(&self.record)?;
+        seq.end()
+    }
+}
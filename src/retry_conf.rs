@@ -0,0 +1,146 @@
+//! Configure retrying and buffering behaviour of `Fluent`.
+
+use std::time::Duration;
+
+const DEFAULT_MAX_RETRY: usize = 13;
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConf {
+    max_retry: usize,
+    multiplier: f64,
+    store_file: Option<String>,
+    shared_key: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    self_hostname: Option<String>,
+    require_ack: bool,
+    ack_timeout: Duration,
+    compressed: bool,
+}
+
+impl Default for RetryConf {
+    fn default() -> RetryConf {
+        RetryConf {
+            max_retry: DEFAULT_MAX_RETRY,
+            multiplier: DEFAULT_MULTIPLIER,
+            store_file: None,
+            shared_key: None,
+            username: None,
+            password: None,
+            self_hostname: None,
+            require_ack: false,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            compressed: false,
+        }
+    }
+}
+
+impl RetryConf {
+    pub fn new() -> RetryConf {
+        Default::default()
+    }
+
+    pub fn max_retry(mut self, max_retry: usize) -> RetryConf {
+        self.max_retry = max_retry;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> RetryConf {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn store_file<T: Into<String>>(mut self, path: T) -> RetryConf {
+        self.store_file = Some(path.into());
+        self
+    }
+
+    /// Enable the forward protocol's `<security>` handshake, authenticating
+    /// with Fluentd's shared key.
+    pub fn shared_key<T: Into<String>>(mut self, shared_key: T) -> RetryConf {
+        self.shared_key = Some(shared_key.into());
+        self
+    }
+
+    /// Username sent in the handshake's `PING`, when Fluentd also requires
+    /// per-user authentication.
+    pub fn username<T: Into<String>>(mut self, username: T) -> RetryConf {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn password<T: Into<String>>(mut self, password: T) -> RetryConf {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Hostname this client identifies itself as in the handshake. Defaults
+    /// to the empty string, which Fluentd accepts.
+    pub fn self_hostname<T: Into<String>>(mut self, self_hostname: T) -> RetryConf {
+        self.self_hostname = Some(self_hostname.into());
+        self
+    }
+
+    pub fn get_store_file(&self) -> Option<&str> {
+        self.store_file.as_deref()
+    }
+
+    pub fn get_shared_key(&self) -> Option<&str> {
+        self.shared_key.as_deref()
+    }
+
+    pub fn get_username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn get_password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    pub fn get_self_hostname(&self) -> Option<&str> {
+        self.self_hostname.as_deref()
+    }
+
+    /// Opt into the forward protocol's chunk acknowledgement: a `Forward`
+    /// message carries a chunk id and `Forwardable::post` blocks until
+    /// Fluentd echoes it back in an `{"ack": <id>}` response.
+    pub fn require_ack(mut self, require_ack: bool) -> RetryConf {
+        self.require_ack = require_ack;
+        self
+    }
+
+    pub fn get_require_ack(&self) -> bool {
+        self.require_ack
+    }
+
+    /// How long `Forwardable::post` waits for the `{"ack": <id>}` response
+    /// before treating the send as failed and falling back to `store_file`,
+    /// instead of blocking forever if Fluentd never replies. Only consulted
+    /// when `require_ack` is set. Defaults to 5 seconds.
+    pub fn ack_timeout(mut self, ack_timeout: Duration) -> RetryConf {
+        self.ack_timeout = ack_timeout;
+        self
+    }
+
+    pub fn get_ack_timeout(&self) -> Duration {
+        self.ack_timeout
+    }
+
+    /// Send `Forwardable::post` batches as a single gzip-compressed
+    /// `CompressedPackedForward` message instead of an uncompressed
+    /// `Forward` one.
+    pub fn compressed(mut self, compressed: bool) -> RetryConf {
+        self.compressed = compressed;
+        self
+    }
+
+    pub fn get_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub fn build(&self) -> (usize, f64) {
+        (self.max_retry, self.multiplier)
+    }
+}
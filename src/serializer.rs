@@ -0,0 +1,56 @@
+//! Pluggable wire-format backend for `Fluent`.
+//!
+//! The built-in `JsonSerializer` and `MsgpackSerializer` are the formats
+//! `JsonForwardable` and `MsgpackForwardable` already speak; third-party
+//! crates can add their own (e.g. CBOR) by implementing `PayloadSerializer`
+//! for a type of their own and driving it through `Fluent::post_with_serializer`.
+
+use crate::error::FluentError;
+use rmp_serde::encode::Serializer as MsgpackEncoder;
+use serde::ser::Serialize;
+use serde_json;
+
+pub trait PayloadSerializer {
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, FluentError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JsonSerializer;
+
+impl PayloadSerializer for JsonSerializer {
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, FluentError> {
+        Ok(serde_json::to_vec(value)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MsgpackSerializer;
+
+impl PayloadSerializer for MsgpackSerializer {
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, FluentError> {
+        let mut bytes = Vec::new();
+        value.serialize(&mut MsgpackEncoder::new(&mut bytes))?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmpv;
+
+    #[test]
+    fn json_serializer_produces_json_bytes() {
+        let bytes = JsonSerializer.serialize(&("tag", 1)).unwrap();
+        assert_eq!(bytes, br#"["tag",1]"#);
+    }
+
+    #[test]
+    fn msgpack_serializer_produces_msgpack_bytes() {
+        let bytes = MsgpackSerializer.serialize(&("tag", 1)).unwrap();
+        let value = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+        let fields = value.as_array().unwrap();
+        assert_eq!(fields[0].as_str(), Some("tag"));
+        assert_eq!(fields[1].as_i64(), Some(1));
+    }
+}
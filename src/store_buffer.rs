@@ -0,0 +1,24 @@
+//! Fall back to writing records to disk when delivery to fluentd keeps failing.
+
+use crate::error::FluentError;
+use crate::retry_conf::RetryConf;
+use serde::ser::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Last resort: append `record` to `conf`'s store file (if configured),
+/// otherwise surface the original `err`.
+pub fn maybe_write_events<T: Serialize>(
+    conf: &RetryConf, record: T, err: FluentError,
+) -> Result<(), FluentError> {
+    match conf.get_store_file() {
+        Some(path) => {
+            let message = serde_json::to_string(&record)?;
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(message.as_bytes())?;
+            file.write_all(b"\n")?;
+            Ok(())
+        },
+        None => Err(err),
+    }
+}